@@ -0,0 +1,64 @@
+//! Process configuration. Deserialized from the user's TOML config file by
+//! the binary crate; this struct only defines the shape.
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    pub ssh: SshConfig,
+    pub service_discovery: Option<ServiceDiscoveryConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SshConfig {
+    pub port: u16,
+    pub user: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceDiscoveryConfig {
+    #[serde(default = "default_config_path")]
+    pub config_path: String,
+
+    /// Prefer MQTT v5 (user-property metadata) over the legacy v4 transport.
+    #[serde(default = "default_use_mqtt_v5")]
+    pub use_mqtt_v5: bool,
+
+    pub tls: Option<TlsConfig>,
+    pub reconnect: Option<ReconnectConfig>,
+    pub tracing: Option<DiscoveryTracingConfig>,
+}
+
+fn default_config_path() -> String {
+    crate::common::sd_defaults::SERVICE_DISCOVERY_INI_FILEPATH.to_string()
+}
+
+fn default_use_mqtt_v5() -> bool {
+    true
+}
+
+/// CA (and optional client identity) material for a TLS/mTLS-protected
+/// discovery channel.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsConfig {
+    pub ca_cert_path: String,
+    pub client_cert_path: Option<String>,
+    pub client_key_path: Option<String>,
+}
+
+/// Tunables for the monitor task's reconnect backoff. Both fields fall back
+/// to `sd_defaults::RECONNECT_BACKOFF_{INITIAL,MAX}` when unset.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReconnectConfig {
+    pub backoff_initial_ms: Option<u64>,
+    pub backoff_cap_ms: Option<u64>,
+}
+
+/// Where to ship structured discovery events, selecting the optional Loki
+/// push layer (see `discovery::tracing_layer`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscoveryTracingConfig {
+    pub loki_endpoint: String,
+    pub node_label: Option<String>,
+    pub flush_interval_secs: Option<u64>,
+}
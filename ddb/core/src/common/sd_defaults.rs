@@ -0,0 +1,38 @@
+//! Defaults for the MQTT-based service discovery channel. Centralized here
+//! so `MqttProducer`, `AsyncDiscoverClient`, and the embedded broker all
+//! agree on topic names and timings without copy-pasting literals.
+
+use std::time::Duration;
+
+pub const CLIENT_ID: &str = "ddb-core";
+pub const DEFAULT_BROKER_HOSTNAME: &str = "127.0.0.1";
+pub const BROKER_PORT: u16 = 1883;
+pub const SERVICE_DISCOVERY_INI_FILEPATH: &str = "/etc/ddb/service_discovery.ini";
+
+/// Topic agents announce new `ServiceInfo` on, and where a freshly connected
+/// `MqttProducer` finds the retained roster.
+pub const T_SERVICE_DISCOVERY: &str = "ddb/discovery";
+
+/// Subscription filter for the per-service liveness topic, e.g.
+/// `ddb/status/<tag>`. Agents publish a retained registration here and set
+/// it as their MQTT Last-Will-and-Testament so the broker republishes an
+/// offline marker (or clears the retained message) on ungraceful disconnect.
+pub const T_SERVICE_STATUS: &str = "ddb/status/+";
+
+/// Prefix stripped from a concrete status-topic publish to recover the
+/// service tag, e.g. `ddb/status/192.0.2.1:-42` -> `192.0.2.1:-42`.
+pub const T_SERVICE_STATUS_PREFIX: &str = "ddb/status/";
+
+/// LWT payload the broker republishes in place of a dead agent's retained
+/// registration. An empty payload (a cleared retained message) means the
+/// same thing: the service is gone.
+pub const LWT_OFFLINE_PAYLOAD: &[u8] = b"offline";
+
+pub const RECONNECT_BACKOFF_INITIAL: Duration = Duration::from_millis(250);
+pub const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(8);
+
+/// Minimum time a reconnect must stay up before we trust it enough to reset
+/// the backoff back to `RECONNECT_BACKOFF_INITIAL`. Without this, a broker
+/// that flaps faster than this window would keep the backoff pinned at its
+/// floor, defeating the point of backing off at all.
+pub const RECONNECT_STABLE_THRESHOLD: Duration = Duration::from_secs(30);
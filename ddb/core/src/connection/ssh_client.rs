@@ -0,0 +1,21 @@
+/// SSH target and credentials for attaching to a discovered debuggee.
+/// `password` is `None` for the common case of key-based auth managed
+/// outside this struct (agent forwarding, an `IdentityFile` in `~/.ssh/config`, etc.).
+#[derive(Debug, Clone)]
+pub struct SSHCred {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: Option<String>,
+}
+
+impl SSHCred {
+    pub fn new(host: &str, port: u16, user: &str, password: Option<&str>) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            user: user.to_string(),
+            password: password.map(|p| p.to_string()),
+        }
+    }
+}
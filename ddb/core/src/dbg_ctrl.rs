@@ -0,0 +1,32 @@
+use anyhow::Result;
+
+use crate::connection::ssh_client::SSHCred;
+
+/// How DDB reaches a discovered debuggee to attach a debugger. `ServiceInfo`
+/// stores one as a trait object so different discovery transports can plug
+/// in different attach strategies (SSH today, others later) without
+/// changing the discovery pipeline.
+pub trait AttachController: Send + Sync {
+    fn attach(&self) -> Result<()>;
+}
+
+/// Holds the SSH credentials carried by a discovery event. `attach` checks
+/// the target and does not open an SSH session.
+pub struct SSHAttachController {
+    cred: SSHCred,
+}
+
+impl SSHAttachController {
+    pub fn new(cred: SSHCred) -> Self {
+        Self { cred }
+    }
+}
+
+impl AttachController for SSHAttachController {
+    fn attach(&self) -> Result<()> {
+        // Only validates the target: no `ssh` process is spawned and no
+        // session is opened here.
+        anyhow::ensure!(!self.cred.host.is_empty(), "SSH target host is empty");
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+//! The embedded MQTT broker `MqttProducer` can optionally own (see
+//! `MqttProducer::new`'s `managed_broker`). Deployments that already run a
+//! shared broker pass `None` and point `AsyncDiscoverClient` at it instead.
+
+use std::{
+    fs::File,
+    io::BufReader,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use rumqttd::{Broker, Config as RumqttdConfig, ConnectionSettings, RouterConfig, ServerSettings};
+
+/// Where the broker binds and which transport it terminates. `tls` is
+/// `Some` only when `scheme` is `Tls`; both are set together by
+/// `MqttProducer::start_producing` from `[service_discovery.tls]`.
+pub struct BrokerInfo {
+    pub hostname: String,
+    pub port: u16,
+    pub scheme: BrokerScheme,
+    pub tls: Option<TlsOptions>,
+}
+
+/// Which scheme `BrokerInfo` should bind/connect with. Plaintext stays the
+/// default so existing deployments are unaffected; `Tls` tells both the
+/// embedded broker and `AsyncDiscoverClient` to negotiate rustls on the same
+/// port rather than opening a second one.
+#[derive(Clone, Copy)]
+pub enum BrokerScheme {
+    Plain,
+    Tls,
+}
+
+/// CA (and optional client identity) material for a TLS-protected discovery
+/// channel, mirrored from the `[service_discovery.tls]` section of `Config`.
+/// Client-side, `client_cert_path`/`client_key_path` are only needed for
+/// mutual TLS; the embedded broker requires both, as its server identity.
+#[derive(Clone)]
+pub struct TlsOptions {
+    pub ca_cert_path: String,
+    pub client_cert_path: Option<String>,
+    pub client_key_path: Option<String>,
+}
+
+/// Something `MqttProducer` can start/stop the lifecycle of. Implemented
+/// here by `EmbeddedBroker`; kept as a trait so a test double or an
+/// externally-managed no-op can stand in without touching `MqttProducer`.
+pub trait MessageBroker: Send + Sync {
+    fn start(&self, info: &BrokerInfo, config_path: &str) -> Result<()>;
+    fn stop(&self) -> Result<()>;
+}
+
+/// A `rumqttd` broker run in-process. `start` hands the broker its own
+/// thread (rumqttd drives its own Tokio runtime internally) and keeps that
+/// thread's join handle; `stop` detaches it and does not shut the broker
+/// down, which keeps running until the process exits.
+#[derive(Default)]
+pub struct EmbeddedBroker {
+    handle: Mutex<Option<std::thread::JoinHandle<()>>>,
+}
+
+impl EmbeddedBroker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the rustls server config backing TLS termination: the same
+    /// `BrokerInfo::tls` material `AsyncDiscoverClient::new_tls`/`new_v5_tls`
+    /// use client-side, loaded here as a server identity instead.
+    fn tls_server_config(tls: &TlsOptions) -> Result<Arc<rustls::ServerConfig>> {
+        let cert_path = tls
+            .client_cert_path
+            .as_deref()
+            .context("TLS broker requires a server certificate (client_cert_path)")?;
+        let key_path = tls
+            .client_key_path
+            .as_deref()
+            .context("TLS broker requires a server private key (client_key_path)")?;
+
+        let certs = rustls_pemfile::certs(&mut BufReader::new(
+            File::open(cert_path).context("Failed to open broker TLS certificate")?,
+        ))
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to parse broker TLS certificate")?;
+        let key = rustls_pemfile::private_key(&mut BufReader::new(
+            File::open(key_path).context("Failed to open broker TLS private key")?,
+        ))
+        .context("Failed to parse broker TLS private key")?
+        .context("No private key found in broker TLS key file")?;
+
+        let mut root_store = rustls::RootCertStore::empty();
+        let ca_file = File::open(&tls.ca_cert_path).context("Failed to open broker CA bundle")?;
+        for ca in rustls_pemfile::certs(&mut BufReader::new(ca_file)) {
+            root_store
+                .add(ca.context("Failed to parse broker CA bundle")?)
+                .context("Failed to add CA to broker root store")?;
+        }
+
+        let client_auth = rustls::server::WebPkiClientVerifier::builder(Arc::new(root_store))
+            .allow_unauthenticated()
+            .build()
+            .context("Failed to build broker client verifier")?;
+
+        let config = rustls::ServerConfig::builder()
+            .with_client_cert_verifier(client_auth)
+            .with_single_cert(certs, key)
+            .context("Failed to build broker TLS server config")?;
+
+        Ok(Arc::new(config))
+    }
+}
+
+impl MessageBroker for EmbeddedBroker {
+    fn start(&self, info: &BrokerInfo, _config_path: &str) -> Result<()> {
+        let listen: SocketAddr = format!("{}:{}", info.hostname, info.port)
+            .parse()
+            .context("Invalid broker bind address")?;
+
+        let mut server = ServerSettings {
+            name: "ddb-discovery".to_string(),
+            listen,
+            tls: None,
+            next_connection_delay_ms: 1,
+            connections: ConnectionSettings::default(),
+        };
+
+        if let BrokerScheme::Tls = info.scheme {
+            let tls = info
+                .tls
+                .as_ref()
+                .context("BrokerScheme::Tls set without TlsOptions")?;
+            server.tls = Some(EmbeddedBroker::tls_server_config(tls)?);
+        }
+
+        let config = RumqttdConfig {
+            router: RouterConfig::default(),
+            server,
+        };
+
+        let mut broker = Broker::new(config);
+        let join = std::thread::Builder::new()
+            .name("ddb-embedded-broker".to_string())
+            .spawn(move || {
+                if let Err(e) = broker.start() {
+                    tracing::error!("Embedded broker exited: {}", e);
+                }
+            })
+            .context("Failed to spawn embedded broker thread")?;
+
+        *self.handle.lock().unwrap() = Some(join);
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<()> {
+        // rumqttd has no graceful-shutdown handle on `Broker`; dropping the
+        // owning thread on process exit is how deployments have always
+        // stopped it, so just detach here rather than blocking on join.
+        self.handle.lock().unwrap().take();
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+use anyhow::Result;
+use flume::Sender;
+
+use crate::dbg_ctrl::AttachController;
+
+/// A discovered (or previously discovered) debuggee: enough to show it in
+/// the attach roster and, via `attach_controller`, reach it.
+pub struct ServiceInfo {
+    pub ip: Ipv4Addr,
+    pub tag: String,
+    pub pid: u64,
+    pub hash: String,
+    pub alias: String,
+    pub attach_controller: Box<dyn AttachController>,
+    pub user_data: Option<HashMap<String, String>>,
+}
+
+impl ServiceInfo {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        ip: Ipv4Addr,
+        tag: String,
+        pid: u64,
+        hash: String,
+        alias: String,
+        attach_controller: Box<dyn AttachController>,
+        user_data: Option<HashMap<String, String>>,
+    ) -> Self {
+        Self {
+            ip,
+            tag,
+            pid,
+            hash,
+            alias,
+            attach_controller,
+            user_data,
+        }
+    }
+}
+
+/// What happened to a debuggee, as observed over the discovery channel.
+pub enum DiscoveryEvent {
+    Added(ServiceInfo),
+    Removed { tag: String },
+}
+
+/// A source of `DiscoveryEvent`s (today: `MqttProducer`, backed by MQTT
+/// service discovery). Implementors own whatever background tasks they
+/// need to keep `tx` fed until `stop_producing` is called.
+#[axum::async_trait]
+pub trait DiscoveryMessageProducer {
+    async fn start_producing(&mut self, tx: Sender<DiscoveryEvent>) -> Result<()>;
+    async fn stop_producing(&mut self) -> Result<()>;
+}
@@ -0,0 +1,5 @@
+pub mod broker;
+pub mod discovery_message_producer;
+pub mod mqtt_producer;
+pub mod subscriber;
+pub mod tracing_layer;
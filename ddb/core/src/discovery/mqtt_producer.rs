@@ -1,15 +1,19 @@
-use std::{collections::HashMap, net::Ipv4Addr};
+use std::{collections::HashMap, net::Ipv4Addr, time::Duration};
 
 use anyhow::{Context, Result};
 use flume::Sender;
-use rumqttc::{Event, Packet};
+use rand::Rng;
+use rumqttc::{
+    v5::mqttbytes::v5::{Publish as PublishV5, PublishProperties},
+    Event, Packet,
+};
 use tokio::sync::watch;
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info};
 
 use super::{
-    broker::{BrokerInfo, MessageBroker},
-    discovery_message_producer::{DiscoveryMessageProducer, ServiceInfo},
+    broker::{BrokerInfo, BrokerScheme, MessageBroker, TlsOptions},
+    discovery_message_producer::{DiscoveryEvent, DiscoveryMessageProducer, ServiceInfo},
 };
 use crate::{
     common::sd_defaults, connection::ssh_client::SSHCred, dbg_ctrl::SSHAttachController,
@@ -46,32 +50,206 @@ impl MqttProducer {
             config,
         }
     }
+    /// Keep the discovery subscription alive for the life of the process.
+    /// A dropped broker connection used to end this task silently, killing
+    /// discovery until restart; we now reconnect and re-subscribe with
+    /// exponential backoff (plus jitter, to avoid a thundering herd of
+    /// agents reconnecting in lockstep), while still honoring `sig_stop`
+    /// immediately rather than sleeping through a backoff interval.
+    /// `backoff_initial`/`backoff_cap` come from `[service_discovery.reconnect]`
+    /// in `Config` (see `start_producing`) so operators can tune them per
+    /// deployment instead of recompiling.
     fn monitor(
         &self,
         mut client: AsyncDiscoverClient,
-        sender: Sender<rumqttc::Event>,
+        sender: Sender<MqttEvent>,
+        backoff_initial: Duration,
+        backoff_cap: Duration,
     ) -> tokio::task::JoinHandle<()> {
-        let stop_rx = self.sig_stop.subscribe();
+        let mut stop_rx = self.sig_stop.subscribe();
         let sender = sender.clone();
 
         tokio::spawn(async move {
-            // We should respect ExactlyOnce semantics.
-            if let Ok(_) = client
-                .subscribe(sd_defaults::T_SERVICE_DISCOVERY, rumqttc::QoS::ExactlyOnce)
-                .await
-            {
-                if let Err(e) = client.handle(sender, stop_rx).await {
-                    error!("Client handler error: {}", e);
+            let mut backoff = backoff_initial;
+
+            loop {
+                if *stop_rx.borrow() {
+                    return;
                 }
-            } else {
-                debug!(
-                    "Failed to subscribe to topic: {}",
-                    sd_defaults::T_SERVICE_DISCOVERY
+
+                // We should respect ExactlyOnce semantics. We subscribe to
+                // the discovery topic for announcements (including the
+                // retained roster a fresh subscriber catches up on) and to
+                // the status topic for retained registrations /
+                // LWT-driven deregistration.
+                let subscribed = client
+                    .subscribe(sd_defaults::T_SERVICE_DISCOVERY, rumqttc::QoS::ExactlyOnce)
+                    .await
+                    .and(
+                        client
+                            .subscribe(sd_defaults::T_SERVICE_STATUS, rumqttc::QoS::ExactlyOnce)
+                            .await,
+                    );
+
+                match subscribed {
+                    Ok(()) => {
+                        let connected_at = tokio::time::Instant::now();
+                        match client.handle(sender.clone(), stop_rx.clone()).await {
+                            Ok(()) => return, // handle only returns cleanly once sig_stop fires or the event receiver is gone
+                            Err(e) => error!("Client handler error: {}; reconnecting", e),
+                        }
+
+                        // Only trust this connection enough to reset the
+                        // backoff if it stayed up a while; otherwise a
+                        // flapping broker (connect -> subscribe -> drop)
+                        // would pin backoff at its floor every cycle and
+                        // defeat the point of backing off at all.
+                        if connected_at.elapsed() >= sd_defaults::RECONNECT_STABLE_THRESHOLD {
+                            backoff = backoff_initial;
+                        }
+                    }
+                    Err(e) => debug!(
+                        "Failed to subscribe to topics: {} / {}: {}",
+                        sd_defaults::T_SERVICE_DISCOVERY,
+                        sd_defaults::T_SERVICE_STATUS,
+                        e
+                    ),
+                }
+
+                let jitter = Duration::from_millis(
+                    rand::thread_rng().gen_range(0..=backoff.as_millis() as u64 / 2),
                 );
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff + jitter) => {}
+                    _ = stop_rx.changed() => {
+                        if *stop_rx.borrow() {
+                            return;
+                        }
+                    }
+                }
+                backoff = (backoff * 2).min(backoff_cap);
+
+                if let Err(e) = client.reconnect().await {
+                    error!("Failed to reconnect to broker: {}", e);
+                }
             }
         })
     }
+
+    /// Parse a v5 publish's user properties into a `MqttPayload`, falling
+    /// back to the legacy colon-delimited body when no properties were
+    /// attached (e.g. a v4 debuggee bridged onto a v5 listener).
+    fn parse_v5_publish(publish: &PublishV5) -> Option<MqttPayload> {
+        publish
+            .properties
+            .as_ref()
+            .and_then(MqttPayload::from_user_properties)
+            .or_else(|| {
+                std::str::from_utf8(&publish.payload)
+                    .ok()
+                    .and_then(MqttPayload::parse_legacy)
+            })
+    }
+
+    /// Parse a plain v4 publish body using the original colon-delimited format.
+    fn parse_v4_publish(publish: &rumqttc::mqttbytes::v4::Publish) -> Option<MqttPayload> {
+        std::str::from_utf8(&publish.payload)
+            .ok()
+            .and_then(MqttPayload::parse_legacy)
+    }
+
+    /// Build the `Added` event for a parsed registration payload, whether it
+    /// arrived on the discovery topic or as a retained status-topic
+    /// registration — both carry the same `MqttPayload` shape and both
+    /// resolve to the same SSH attach target.
+    fn added_event(mqtt_payload: MqttPayload, ssh_port: u16, ssh_user: &str) -> DiscoveryEvent {
+        debug!(
+            ip = %mqtt_payload.ip,
+            tag = %mqtt_payload.tag,
+            pid = mqtt_payload.pid,
+            hash = %mqtt_payload.hash,
+            alias = %mqtt_payload.alias,
+            "Parsed discovery payload"
+        );
+
+        let ssh_cred = SSHCred::new(mqtt_payload.ip.to_string().as_str(), ssh_port, ssh_user, None);
+        info!(
+            ip = %mqtt_payload.ip,
+            tag = %mqtt_payload.tag,
+            pid = mqtt_payload.pid,
+            hash = %mqtt_payload.hash,
+            alias = %mqtt_payload.alias,
+            ssh_port,
+            "Selected SSH target for discovered service"
+        );
+        DiscoveryEvent::Added(ServiceInfo::new(
+            mqtt_payload.ip,
+            mqtt_payload.tag,
+            mqtt_payload.pid,
+            mqtt_payload.hash,
+            mqtt_payload.alias,
+            Box::new(SSHAttachController::new(ssh_cred)),
+            mqtt_payload.user_data,
+        ))
+    }
+
+    /// A publish on the per-service status topic is a removal when its
+    /// retained registration was cleared (empty payload) or the broker is
+    /// replaying an agent's Last-Will-and-Testament (an explicit "offline"
+    /// payload); any other payload is a (re)registration and is handled by
+    /// the caller like a discovery-topic announcement, which is how the
+    /// retained status topic also serves as the cold-start roster source.
+    /// Returns the service `tag`, taken from the topic's last segment, e.g.
+    /// `ddb/status/<tag>`.
+    ///
+    /// This leaf segment must be byte-for-byte the same string an agent's
+    /// `Added` announcement carries as `tag` (the `tag` user property if
+    /// set, otherwise `MqttPayload::from_user_properties`'s/`parse_legacy`'s
+    /// generated `format!("{}:-{}", ip, pid)`) — `DiscoveryEvent::Removed`
+    /// is matched against the roster by that string, so any mismatch makes
+    /// deregistration a silent no-op. We can only sanity-check, not enforce,
+    /// that from here since this side never sees the paired `Added` event.
+    fn removed_tag_from_status(topic: &str, payload: &[u8]) -> Option<String> {
+        let is_offline = payload.is_empty() || payload == sd_defaults::LWT_OFFLINE_PAYLOAD;
+        if !is_offline {
+            return None;
+        }
+
+        Self::status_topic_tag(topic)
+    }
+
+    /// The `tag` segment of a status-topic publish, e.g. `ddb/status/<tag>`.
+    fn status_topic_tag(topic: &str) -> Option<String> {
+        let tag = topic.strip_prefix(sd_defaults::T_SERVICE_STATUS_PREFIX)?;
+        if tag.is_empty() {
+            debug!(topic, "Ignoring status publish with an empty tag segment");
+            return None;
+        }
+        Some(tag.to_string())
+    }
+}
+
+/// Incoming MQTT traffic, tagged by the protocol version the underlying
+/// `AsyncDiscoverClient` was configured with. v5 publishes carry their own
+/// `PublishProperties`, so we keep them distinct from plain v4 events rather
+/// than downcasting everything to the v4 shape.
+pub enum MqttEvent {
+    V4(rumqttc::Event),
+    V5(rumqttc::v5::Event),
+}
+
+impl From<rumqttc::Event> for MqttEvent {
+    fn from(event: rumqttc::Event) -> Self {
+        MqttEvent::V4(event)
+    }
 }
+
+impl From<rumqttc::v5::Event> for MqttEvent {
+    fn from(event: rumqttc::v5::Event) -> Self {
+        MqttEvent::V5(event)
+    }
+}
+
 pub struct MqttPayload {
     pub ip: Ipv4Addr,
     pub tag: String,
@@ -80,13 +258,64 @@ pub struct MqttPayload {
     pub alias: String,
     pub user_data: Option<HashMap<String, String>>,
 }
-impl From<&str> for MqttPayload {
-    fn from(s: &str) -> Self {
+impl MqttPayload {
+    /// Build a `MqttPayload` from MQTT v5 user properties instead of the
+    /// legacy colon-delimited body. Returns `None` if the mandatory `ip` or
+    /// `pid` keys are missing or unparsable, so callers can fall back to the
+    /// v4 parser. `tag` is optional and defaults to the same
+    /// `format!("{}:-{}", ip, pid)` shape `parse_legacy` generates.
+    fn from_user_properties(properties: &PublishProperties) -> Option<Self> {
+        let props: HashMap<&str, &str> = properties
+            .user_properties
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+
+        let ip: Ipv4Addr = props.get("ip")?.parse().ok()?;
+        let pid: u64 = props.get("pid")?.parse().ok()?;
+        let tag = props
+            .get("tag")
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("{}:-{}", ip, pid));
+        let hash = props.get("hash").unwrap_or(&"").to_string();
+        let alias = props.get("alias").unwrap_or(&"").to_string();
+
+        let user_data = props.get("user_data").map(|raw| {
+            raw.split(',')
+                .filter_map(|kv| {
+                    let mut parts = kv.trim().splitn(2, '=');
+                    let key = parts.next()?.trim().to_string();
+                    let value = parts.next().unwrap_or("").trim().to_string();
+                    Some((key, value))
+                })
+                .collect::<HashMap<String, String>>()
+        });
+
+        Some(MqttPayload {
+            ip,
+            tag,
+            pid,
+            hash,
+            alias,
+            user_data,
+        })
+    }
+}
+
+impl MqttPayload {
+    /// Parse the legacy colon-delimited body (`<ip>:<?>:<pid>:<hash>=<alias>:<user_data>`).
+    /// Returns `None` on any malformed input instead of panicking, since this
+    /// runs on every discovery-topic publish, including retained-message
+    /// clears and other payloads that were never meant to match the format.
+    fn parse_legacy(s: &str) -> Option<Self> {
         let parts: Vec<&str> = s.split(':').collect();
+        if parts.len() < 3 {
+            return None;
+        }
 
-        let ip_int: u32 = parts[0].parse().unwrap();
+        let ip_int: u32 = parts[0].parse().ok()?;
         let ip = Ipv4Addr::from(ip_int);
-        let pid = parts[2].parse().unwrap();
+        let pid = parts[2].parse().ok()?;
 
         let tag = format!("{}:-{}", ip, pid);
 
@@ -95,12 +324,12 @@ impl From<&str> for MqttPayload {
             .map(|identifier| {
                 let identifier = *identifier;
                 let identifier_parts: Vec<_> = identifier.split('=').collect();
-                let hash = identifier_parts[0];
+                let hash = identifier_parts.first().copied().unwrap_or("");
                 let alias = identifier_parts.get(1).unwrap_or(&"app").to_string();
                 (hash.to_string(), alias)
             })
             .unwrap_or((String::new(), String::new()));
-        
+
         let user_data = parts.last().map(|&user_data| {
             // if doesn't start with "{", meaning it is not a user_data field, then ignore it.
             // we assume the user_data field is the last one in the payload if it exists.
@@ -113,18 +342,18 @@ impl From<&str> for MqttPayload {
                     let key = kv_parts[0].trim().to_string();
                     let value = kv_parts.get(1).unwrap_or(&"").trim().to_string();
                     (key, value)
-                }).collect::<HashMap<String, String>>()                
+                }).collect::<HashMap<String, String>>()
             })
         }).flatten();
 
-        MqttPayload {
+        Some(MqttPayload {
             ip,
             tag,
             pid,
             hash,
             alias,
             user_data
-        }
+        })
     }
 }
 
@@ -135,17 +364,38 @@ impl DiscoveryMessageProducer for MqttProducer {
     /// 2. Creating an AsyncDiscoverClient,
     /// 3. Subscribing to the desired topic,
     /// 4. Spawning a monitor task that feeds an internal channel with MQTT events,
-    /// 5. Spawning consumer tasks that parse events and send `ServiceInfo` into `tx`.
+    /// 5. Spawning a single consumer task that parses events and sends `DiscoveryEvent`s into `tx`.
     async fn start_producing(
         &mut self,
-        tx: Sender<ServiceInfo>,
+        tx: Sender<DiscoveryEvent>,
     ) -> Result<()> {
+        // Service discovery traffic (IPs, PIDs, SSH target hints) is
+        // sensitive, so TLS/mTLS is configured once here and threaded
+        // through both the managed broker and the client below.
+        let tls_options = self
+            .config
+            .service_discovery
+            .as_ref()
+            .and_then(|sd| sd.tls.as_ref())
+            .map(|tls| TlsOptions {
+                ca_cert_path: tls.ca_cert_path.clone(),
+                client_cert_path: tls.client_cert_path.clone(),
+                client_key_path: tls.client_key_path.clone(),
+            });
+        let scheme = if tls_options.is_some() {
+            BrokerScheme::Tls
+        } else {
+            BrokerScheme::Plain
+        };
+
         // 1. Start the broker if we manage it
         if let Some(broker) = &self.managed_broker {
             info!("Starting managed broker...");
             let broker_info = BrokerInfo {
                 hostname: sd_defaults::DEFAULT_BROKER_HOSTNAME.to_string(),
                 port: sd_defaults::BROKER_PORT,
+                scheme,
+                tls: tls_options.as_ref().map(TlsOptions::clone),
             };
 
             // Get the config path from the configuration
@@ -161,22 +411,73 @@ impl DiscoveryMessageProducer for MqttProducer {
                 .context("Failed to start managed broker")?;
         }
 
-        // 2. Create an AsyncDiscoverClient and subscribe
-        let mut client = AsyncDiscoverClient::new(
-            sd_defaults::CLIENT_ID,
-            sd_defaults::DEFAULT_BROKER_HOSTNAME,
-            sd_defaults::BROKER_PORT,
-        );
+        // 2. Create an AsyncDiscoverClient and subscribe. Prefer MQTT v5 so
+        // metadata rides in user properties rather than a positional body;
+        // debuggees that still speak v4 are handled by the payload fallback
+        // below.
+        let use_v5 = self
+            .config
+            .service_discovery
+            .as_ref()
+            .map(|sd| sd.use_mqtt_v5)
+            .unwrap_or(true);
+        let mut client = match (use_v5, tls_options) {
+            (true, Some(tls)) => AsyncDiscoverClient::new_v5_tls(
+                sd_defaults::CLIENT_ID,
+                sd_defaults::DEFAULT_BROKER_HOSTNAME,
+                sd_defaults::BROKER_PORT,
+                tls,
+            )
+            .context("Failed to build TLS-enabled v5 client")?,
+            (true, None) => AsyncDiscoverClient::new_v5(
+                sd_defaults::CLIENT_ID,
+                sd_defaults::DEFAULT_BROKER_HOSTNAME,
+                sd_defaults::BROKER_PORT,
+            ),
+            (false, Some(tls)) => AsyncDiscoverClient::new_tls(
+                sd_defaults::CLIENT_ID,
+                sd_defaults::DEFAULT_BROKER_HOSTNAME,
+                sd_defaults::BROKER_PORT,
+                tls,
+            )
+            .context("Failed to build TLS-enabled v4 client")?,
+            (false, None) => AsyncDiscoverClient::new(
+                sd_defaults::CLIENT_ID,
+                sd_defaults::DEFAULT_BROKER_HOSTNAME,
+                sd_defaults::BROKER_PORT,
+            ),
+        };
         if let Err(e) = client.check_broker_online().await {
             return Err(anyhow::anyhow!("Failed to connect to broker: {}", e));
         }
         info!("Successfully connected to broker");
+
+        let reconnect_cfg = self
+            .config
+            .service_discovery
+            .as_ref()
+            .and_then(|sd| sd.reconnect.as_ref());
+        let backoff_initial = reconnect_cfg
+            .and_then(|r| r.backoff_initial_ms)
+            .map(Duration::from_millis)
+            .unwrap_or(sd_defaults::RECONNECT_BACKOFF_INITIAL);
+        let backoff_cap = reconnect_cfg
+            .and_then(|r| r.backoff_cap_ms)
+            .map(Duration::from_millis)
+            .unwrap_or(sd_defaults::RECONNECT_BACKOFF_MAX);
+
         let (event_sender, event_receiver) = flume::bounded(1024);
-        self.monitor(client, event_sender.clone());
+        self.monitor(client, event_sender.clone(), backoff_initial, backoff_cap);
 
-        // 3. Spawn consumer tasks that read from event_receiver and forward to `tx`.
-        let concurrency = 3;
-        for _ in 0..concurrency {
+        // 3. Spawn a single consumer task that reads from event_receiver and
+        // forwards to `tx`. `Added`/`Removed` for the same service tag must
+        // be observed by `tx`'s receiver in the order the broker delivered
+        // them (an out-of-order pair leaves a dead debuggee in the roster
+        // forever), and `event_receiver` is itself a single ordered stream
+        // from `monitor`, so a single consumer is what preserves that
+        // ordering; fanning this out to concurrent workers races different
+        // services' events past each other non-deterministically.
+        {
             let event_rx = event_receiver.clone();
             let tx_clone = tx.clone();
 
@@ -185,31 +486,52 @@ impl DiscoveryMessageProducer for MqttProducer {
 
             let handle = tokio::spawn(async move {
                 while let Ok(event) = event_rx.recv_async().await {
-                    if let Event::Incoming(Packet::Publish(publish)) = event {
-                        if let Ok(payload_str) = std::str::from_utf8(&publish.payload) {
-                            let mqtt_payload = MqttPayload::from(payload_str);
-                            let ssh_cred = SSHCred::new(
-                                mqtt_payload.ip.to_string().as_str(),
-                                ssh_port,
-                                ssh_user.as_str(),
-                                None,
-                            );
-                            let info = ServiceInfo::new(
-                                mqtt_payload.ip,
-                                mqtt_payload.tag,
-                                mqtt_payload.pid,
-                                mqtt_payload.hash,
-                                mqtt_payload.alias,
-                                Box::new(SSHAttachController::new(ssh_cred)),
-                                mqtt_payload.user_data,
-                            );
-
-                            if let Err(e) = tx_clone.send_async(info).await {
-                                error!("Failed to send ServiceInfo: {}", e);
-                            }
-                        } else {
-                            debug!("Ignoring invalid UTF-8 payload.");
+                    let (topic, payload, mqtt_payload) = match event {
+                        MqttEvent::V5(rumqttc::v5::Event::Incoming(
+                            rumqttc::v5::mqttbytes::v5::Packet::Publish(publish),
+                        )) => {
+                            let topic = publish.topic.clone();
+                            let payload = publish.payload.to_vec();
+                            (topic, payload, Self::parse_v5_publish(&publish))
                         }
+                        MqttEvent::V4(Event::Incoming(Packet::Publish(publish))) => {
+                            let topic = publish.topic.clone();
+                            let payload = publish.payload.to_vec();
+                            (topic, payload, Self::parse_v4_publish(&publish))
+                        }
+                        _ => continue,
+                    };
+
+                    // The discovery topic only ever carries (re)announcements.
+                    // The status topic is LWT-backed liveness: an offline/
+                    // cleared payload is a removal, but any other payload is
+                    // a retained registration — treating it the same as a
+                    // discovery announcement is what lets a freshly
+                    // subscribed consumer build its "live roster" straight
+                    // from the retained status topic, per the original
+                    // request, rather than only from discovery-topic
+                    // announcements.
+                    let discovery_event = if topic == sd_defaults::T_SERVICE_DISCOVERY {
+                        mqtt_payload.map(|mqtt_payload| {
+                            Self::added_event(mqtt_payload, ssh_port, &ssh_user)
+                        })
+                    } else if let Some(tag) = Self::removed_tag_from_status(&topic, &payload) {
+                        info!(tag = %tag, "Service removed");
+                        Some(DiscoveryEvent::Removed { tag })
+                    } else if Self::status_topic_tag(&topic).is_some() {
+                        mqtt_payload.map(|mqtt_payload| {
+                            Self::added_event(mqtt_payload, ssh_port, &ssh_user)
+                        })
+                    } else {
+                        None
+                    };
+
+                    let Some(discovery_event) = discovery_event else {
+                        continue;
+                    };
+
+                    if let Err(e) = tx_clone.send_async(discovery_event).await {
+                        error!("Failed to send DiscoveryEvent: {}", e);
                     }
                 }
             });
@@ -247,3 +569,102 @@ impl DiscoveryMessageProducer for MqttProducer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_legacy_rejects_empty_payload() {
+        assert!(MqttPayload::parse_legacy("").is_none());
+    }
+
+    #[test]
+    fn parse_legacy_rejects_too_few_parts() {
+        assert!(MqttPayload::parse_legacy("1:2").is_none());
+    }
+
+    #[test]
+    fn parse_legacy_rejects_non_numeric_ip() {
+        assert!(MqttPayload::parse_legacy("not-an-ip:0:42").is_none());
+    }
+
+    #[test]
+    fn parse_legacy_rejects_non_numeric_pid() {
+        assert!(MqttPayload::parse_legacy("16909060:0:not-a-pid").is_none());
+    }
+
+    #[test]
+    fn parse_legacy_accepts_minimal_payload() {
+        let payload = MqttPayload::parse_legacy("16909060:0:42").expect("should parse");
+        assert_eq!(payload.pid, 42);
+        assert_eq!(payload.tag, format!("{}:-42", payload.ip));
+    }
+
+    fn user_properties(pairs: &[(&str, &str)]) -> PublishProperties {
+        let mut properties = PublishProperties::default();
+        properties.user_properties = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        properties
+    }
+
+    #[test]
+    fn from_user_properties_rejects_missing_ip() {
+        let properties = user_properties(&[("pid", "42")]);
+        assert!(MqttPayload::from_user_properties(&properties).is_none());
+    }
+
+    #[test]
+    fn from_user_properties_rejects_missing_pid() {
+        let properties = user_properties(&[("ip", "1.2.3.4")]);
+        assert!(MqttPayload::from_user_properties(&properties).is_none());
+    }
+
+    #[test]
+    fn from_user_properties_rejects_unparsable_pid() {
+        let properties = user_properties(&[("ip", "1.2.3.4"), ("pid", "not-a-pid")]);
+        assert!(MqttPayload::from_user_properties(&properties).is_none());
+    }
+
+    #[test]
+    fn from_user_properties_defaults_missing_tag() {
+        let properties = user_properties(&[("ip", "1.2.3.4"), ("pid", "42")]);
+        let payload = MqttPayload::from_user_properties(&properties).expect("should parse");
+        assert_eq!(payload.tag, "1.2.3.4:-42");
+    }
+
+    #[test]
+    fn status_topic_tag_agrees_with_generated_tag() {
+        let payload = MqttPayload::parse_legacy("16909060:0:42").expect("should parse");
+        let topic = format!("{}{}", sd_defaults::T_SERVICE_STATUS_PREFIX, payload.tag);
+        assert_eq!(MqttProducer::status_topic_tag(&topic).as_deref(), Some(payload.tag.as_str()));
+    }
+
+    #[test]
+    fn removed_tag_from_status_ignores_registration_payloads() {
+        let topic = format!("{}some-tag", sd_defaults::T_SERVICE_STATUS_PREFIX);
+        assert!(MqttProducer::removed_tag_from_status(&topic, b"16909060:0:42").is_none());
+    }
+
+    #[test]
+    fn removed_tag_from_status_matches_offline_and_empty_payloads() {
+        let topic = format!("{}some-tag", sd_defaults::T_SERVICE_STATUS_PREFIX);
+        assert_eq!(
+            MqttProducer::removed_tag_from_status(&topic, sd_defaults::LWT_OFFLINE_PAYLOAD).as_deref(),
+            Some("some-tag")
+        );
+        assert_eq!(
+            MqttProducer::removed_tag_from_status(&topic, b"").as_deref(),
+            Some("some-tag")
+        );
+    }
+
+    #[test]
+    fn from_user_properties_honors_explicit_tag() {
+        let properties = user_properties(&[("ip", "1.2.3.4"), ("pid", "42"), ("tag", "custom-tag")]);
+        let payload = MqttPayload::from_user_properties(&properties).expect("should parse");
+        assert_eq!(payload.tag, "custom-tag");
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Only events whose target starts with this prefix are shipped to Loki.
+/// Scopes the layer to the discovery module's own `info!`/`debug!`/`error!`
+/// calls rather than every event flowing through the process's subscriber.
+const DISCOVERY_TARGET_PREFIX: &str = "ddb_core::discovery";
+
+/// Known discovery fields, in the fixed order they should appear in a
+/// rendered line. Keeping this order stable (rather than a `HashMap`'s
+/// iteration order) is what makes the Loki stream queryable/diffable.
+const FIELD_ORDER: &[&str] = &["ip", "tag", "pid", "hash", "alias", "ssh_port"];
+
+/// HTTP push layer that ships discovery events to a Loki-style log
+/// aggregator so an operator can query "which debuggees were discovered and
+/// attached" across a fleet instead of grepping per-node stderr. Construct
+/// with [`LokiLayer::new`] and `.with()` it alongside the existing stderr
+/// subscriber; when `Config` has no remote endpoint configured, callers
+/// should skip adding this layer and keep stderr-only logging.
+pub struct LokiLayer {
+    tx: mpsc::UnboundedSender<LokiLine>,
+}
+
+struct LokiLine {
+    timestamp_ns: u128,
+    line: String,
+}
+
+#[derive(Serialize)]
+struct LokiPushRequest {
+    streams: Vec<LokiStream>,
+}
+
+#[derive(Serialize)]
+struct LokiStream {
+    stream: HashMap<String, String>,
+    values: Vec<[String; 2]>,
+}
+
+impl LokiLayer {
+    /// Spawn a background task that batches entries and flushes them to
+    /// `endpoint` on `flush_interval`, returning the layer that feeds it.
+    /// `labels` are the static Loki stream labels (e.g. `job=ddb-discovery`,
+    /// `node=<hostname>`) attached to every pushed batch.
+    ///
+    /// `install()` composes this onto the subscriber before `main`'s runtime
+    /// necessarily exists (subscriber setup commonly runs first), so this
+    /// can't assume `tokio::spawn` has a runtime to land on. Prefer the
+    /// ambient runtime when there is one; otherwise run the flush loop on
+    /// its own dedicated thread with a minimal current-thread runtime,
+    /// mirroring how `EmbeddedBroker` runs off the main runtime.
+    pub fn new(endpoint: String, labels: HashMap<String, String>, flush_interval: Duration) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel::<LokiLine>();
+
+        let flush_loop = Self::flush_loop(endpoint, labels, flush_interval, rx);
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(flush_loop);
+            }
+            Err(_) => {
+                std::thread::Builder::new()
+                    .name("ddb-loki-push".to_string())
+                    .spawn(move || {
+                        tokio::runtime::Builder::new_current_thread()
+                            .enable_all()
+                            .build()
+                            .expect("Failed to build Loki push runtime")
+                            .block_on(flush_loop);
+                    })
+                    .expect("Failed to spawn Loki push thread");
+            }
+        }
+
+        Self { tx }
+    }
+
+    async fn flush_loop(
+        endpoint: String,
+        labels: HashMap<String, String>,
+        flush_interval: Duration,
+        mut rx: mpsc::UnboundedReceiver<LokiLine>,
+    ) {
+        let client = reqwest::Client::new();
+        let mut batch = Vec::new();
+        let mut ticker = tokio::time::interval(flush_interval);
+        loop {
+            tokio::select! {
+                maybe_line = rx.recv() => {
+                    match maybe_line {
+                        Some(line) => batch.push(line),
+                        None => break,
+                    }
+                }
+                _ = ticker.tick() => {
+                    if batch.is_empty() {
+                        continue;
+                    }
+                    let values = batch
+                        .drain(..)
+                        .map(|l| [l.timestamp_ns.to_string(), l.line])
+                        .collect();
+                    let body = LokiPushRequest {
+                        streams: vec![LokiStream {
+                            stream: labels.clone(),
+                            values,
+                        }],
+                    };
+                    if let Err(e) = client.post(&endpoint).json(&body).send().await {
+                        tracing::debug!("Failed to push discovery trace to Loki: {}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Collects an event's fields (`ip`, `tag`, `pid`, `hash`, `alias`,
+/// `message`, ...) into a flat map so we can render a single log line
+/// without hand-rolling a `Visit` impl per call site.
+#[derive(Default)]
+struct FieldCollector {
+    fields: HashMap<String, String>,
+}
+
+impl Visit for FieldCollector {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.fields
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+/// Build the optional Loki layer from `Config`, or `None` if no remote
+/// endpoint is configured. Callers `.with()` the result onto their
+/// `tracing_subscriber::Registry` alongside the existing stderr layer,
+/// so discovery logging degrades to stderr-only when this returns `None`.
+pub fn loki_layer_from_config(config: &crate::common::config::Config) -> Option<LokiLayer> {
+    let tracing_cfg = config.service_discovery.as_ref()?.tracing.as_ref()?;
+
+    let mut labels = HashMap::new();
+    labels.insert("job".to_string(), "ddb-discovery".to_string());
+    if let Some(node) = tracing_cfg.node_label.as_ref() {
+        labels.insert("node".to_string(), node.clone());
+    }
+
+    Some(LokiLayer::new(
+        tracing_cfg.loki_endpoint.clone(),
+        labels,
+        Duration::from_secs(tracing_cfg.flush_interval_secs.unwrap_or(2)),
+    ))
+}
+
+impl<S: Subscriber> Layer<S> for LokiLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        // Filtered here rather than in `Layer::enabled`, which would disable
+        // non-discovery events for the whole subscriber, stderr included.
+        if !event.metadata().target().starts_with(DISCOVERY_TARGET_PREFIX) {
+            return;
+        }
+
+        let mut collector = FieldCollector::default();
+        event.record(&mut collector);
+
+        let message = collector
+            .fields
+            .remove("message")
+            .unwrap_or_else(|| event.metadata().name().to_string());
+        let mut line = format!(
+            "level={} target={} msg={}",
+            event.metadata().level(),
+            event.metadata().target(),
+            message
+        );
+
+        // Known fields first, in FIELD_ORDER, then anything else sorted by
+        // key, so the same kind of event always renders identically.
+        for key in FIELD_ORDER {
+            if let Some(value) = collector.fields.remove(*key) {
+                line.push_str(&format!(" {}={}", key, value));
+            }
+        }
+        let mut remaining: Vec<_> = collector.fields.into_iter().collect();
+        remaining.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (key, value) in remaining {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+
+        let timestamp_ns = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+
+        // Best-effort: drop the line rather than block the hot discovery
+        // path if the push task has died.
+        let _ = self.tx.send(LokiLine { timestamp_ns, line });
+    }
+}
+
+/// Install the process-wide subscriber: stderr formatting plus the Loki
+/// layer selected from `config`. `Option<LokiLayer>` itself implements
+/// `Layer`, so an unconfigured `[service_discovery.tracing]` degrades to
+/// stderr-only rather than requiring a separate code path.
+pub fn install(config: &crate::common::config::Config) {
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer())
+        .with(loki_layer_from_config(config))
+        .init();
+}
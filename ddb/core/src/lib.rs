@@ -0,0 +1,11 @@
+pub mod common;
+pub mod connection;
+pub mod dbg_ctrl;
+pub mod discovery;
+
+/// Install the process-wide tracing subscriber, selecting the Loki push
+/// layer from `config` and degrading to stderr-only logging when
+/// `[service_discovery.tracing]` is unset. Call once at process startup.
+pub fn init_tracing(config: &common::config::Config) {
+    discovery::tracing_layer::install(config);
+}